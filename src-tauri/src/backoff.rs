@@ -0,0 +1,177 @@
+//! Exponential-backoff retry helper shared by anything that needs to retry a
+//! fallible async operation (e.g. spawning the Claude process) without
+//! hammering it in a tight loop.
+
+use std::time::Duration;
+
+/// Exponential backoff with optional jitter.
+///
+/// Delays start at `base`, double (or scale by `factor`) after each failed
+/// attempt, and are capped at `max`. Call [`Backoff::reset`] to start a fresh
+/// sequence of attempts.
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    base: Duration,
+    max: Duration,
+    factor: f64,
+    /// Jitter applied as ±(jitter * delay), e.g. `0.1` for ±10%.
+    jitter: f64,
+    current: Duration,
+}
+
+impl Backoff {
+    pub fn new(base: Duration, max: Duration, factor: f64) -> Self {
+        Self {
+            base,
+            max,
+            factor,
+            jitter: 0.0,
+            current: base,
+        }
+    }
+
+    /// Apply up to `±fraction` random jitter to each delay (e.g. `0.2` for ±20%).
+    pub fn with_jitter(mut self, fraction: f64) -> Self {
+        self.jitter = fraction;
+        self
+    }
+
+    /// Reset the sequence back to `base`, e.g. after a successful attempt.
+    pub fn reset(&mut self) {
+        self.current = self.base;
+    }
+
+    /// Sleep for the current delay, then advance it for the next attempt.
+    pub async fn wait(&mut self) {
+        tokio::time::sleep(self.next_delay()).await;
+    }
+
+    fn next_delay(&mut self) -> Duration {
+        let delay = self.current;
+        let next = self.current.mul_f64(self.factor);
+        self.current = next.min(self.max);
+        apply_jitter(delay, self.jitter)
+    }
+}
+
+fn apply_jitter(delay: Duration, fraction: f64) -> Duration {
+    if fraction <= 0.0 {
+        return delay;
+    }
+    let spread = delay.as_secs_f64() * fraction;
+    let offset = (rand::random::<f64>() * 2.0 - 1.0) * spread;
+    let jittered = (delay.as_secs_f64() + offset).max(0.0);
+    Duration::from_secs_f64(jittered)
+}
+
+/// Retry `attempt` up to `max_retries` additional times (so `max_retries + 1`
+/// total attempts), backing off between failures. Returns the first success,
+/// or the last error once the retry budget is exhausted.
+pub async fn retry_with_backoff<T, E, F, Fut>(
+    mut backoff: Backoff,
+    max_retries: u32,
+    mut attempt: F,
+) -> Result<T, E>
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = Result<T, E>>,
+{
+    backoff.reset();
+    let mut tries = 0;
+    loop {
+        match attempt().await {
+            Ok(value) => return Ok(value),
+            Err(err) => {
+                if tries >= max_retries {
+                    return Err(err);
+                }
+                tries += 1;
+                backoff.wait().await;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Without jitter, each delay should double the last (per `factor: 2.0`)
+    /// until it hits `max`, where it stays.
+    #[test]
+    fn next_delay_doubles_then_caps_at_max() {
+        let mut backoff = Backoff::new(
+            Duration::from_millis(100),
+            Duration::from_millis(350),
+            2.0,
+        );
+
+        assert_eq!(backoff.next_delay(), Duration::from_millis(100));
+        assert_eq!(backoff.next_delay(), Duration::from_millis(200));
+        // Would be 400ms uncapped; clamped to max.
+        assert_eq!(backoff.next_delay(), Duration::from_millis(350));
+        assert_eq!(backoff.next_delay(), Duration::from_millis(350));
+    }
+
+    /// `reset` should bring the sequence back to `base` regardless of how far
+    /// along it had advanced.
+    #[test]
+    fn reset_returns_to_base() {
+        let mut backoff = Backoff::new(Duration::from_millis(100), Duration::from_secs(10), 2.0);
+        backoff.next_delay();
+        backoff.next_delay();
+        backoff.reset();
+        assert_eq!(backoff.next_delay(), Duration::from_millis(100));
+    }
+
+    /// Jitter must only ever move the delay within `±fraction` of the
+    /// original value, and never below zero.
+    #[test]
+    fn jitter_stays_within_bounds() {
+        let delay = Duration::from_millis(1000);
+        for _ in 0..1000 {
+            let jittered = apply_jitter(delay, 0.2);
+            assert!(jittered.as_secs_f64() >= delay.as_secs_f64() * 0.8 - f64::EPSILON);
+            assert!(jittered.as_secs_f64() <= delay.as_secs_f64() * 1.2 + f64::EPSILON);
+        }
+    }
+
+    /// Zero (or negative) jitter fraction must leave the delay untouched.
+    #[test]
+    fn zero_jitter_is_a_no_op() {
+        let delay = Duration::from_millis(250);
+        assert_eq!(apply_jitter(delay, 0.0), delay);
+    }
+
+    /// `retry_with_backoff` should return the first success without
+    /// exhausting the retry budget.
+    #[tokio::test]
+    async fn retry_with_backoff_returns_first_success() {
+        let backoff = Backoff::new(Duration::from_millis(1), Duration::from_millis(5), 2.0);
+        let mut calls = 0;
+        let result: Result<u32, &str> = retry_with_backoff(backoff, 3, || {
+            calls += 1;
+            async move { Ok(42) }
+        })
+        .await;
+
+        assert_eq!(result, Ok(42));
+        assert_eq!(calls, 1);
+    }
+
+    /// `retry_with_backoff` should retry on failure up to `max_retries`
+    /// additional times, then surface the last error.
+    #[tokio::test]
+    async fn retry_with_backoff_exhausts_retries_then_returns_last_error() {
+        let backoff = Backoff::new(Duration::from_millis(1), Duration::from_millis(5), 2.0);
+        let mut calls = 0;
+        let result: Result<u32, &str> = retry_with_backoff(backoff, 2, || {
+            calls += 1;
+            async move { Err("still failing") }
+        })
+        .await;
+
+        assert_eq!(result, Err("still failing"));
+        assert_eq!(calls, 3); // initial attempt + 2 retries
+    }
+}
@@ -0,0 +1,151 @@
+//! A reusable, testable event stream over a spawned Claude child process.
+//!
+//! [`event_stream`] interleaves parsed stdout JSON events and raw stderr
+//! lines as they arrive, then yields a single classified [`SpawnError`] (or
+//! nothing, on a clean exit) once the child terminates, so callers learn
+//! *why* a session stopped instead of just seeing the stream close.
+
+use async_stream::stream;
+use futures_util::Stream;
+use serde_json::Value;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::Child;
+
+/// One item produced while a Claude process is running.
+#[derive(Debug, Clone)]
+pub enum ClaudeEvent {
+    /// A line of stdout. `json` is `Some` when the line parsed as JSON;
+    /// it's `None` both for plain non-JSON-looking lines and for lines that
+    /// *look* like JSON (start with `{` or `[`) but fail to parse — the
+    /// latter case is always immediately followed by a paired
+    /// `SpawnError::Serialization` item so callers still learn the line was
+    /// malformed, without losing the raw text itself.
+    Stdout { raw: String, json: Option<Value> },
+    /// A line of stderr.
+    Stderr(String),
+}
+
+/// Why a Claude process stream ended abnormally.
+#[derive(Debug)]
+pub enum SpawnError {
+    /// An I/O error occurred while reading a pipe or waiting on the child.
+    Io(std::io::Error),
+    /// The child exited with a non-zero status; `stderr` is everything it
+    /// wrote to stderr over the process's lifetime.
+    ExitCode { code: i32, stderr: String },
+    /// The child was terminated by a signal rather than exiting normally
+    /// (Unix only; no exit code is available in this case).
+    Signal(i32),
+    /// The child was spawned without a piped stdout handle.
+    NoStdout,
+    /// The child was spawned without a piped stderr handle.
+    NoStderr,
+    /// A stdout line looked like JSON but failed to parse. `raw` carries the
+    /// offending line itself, so the error is actionable even though it's
+    /// also yielded (with `json: None`) as its own `ClaudeEvent::Stdout`.
+    Serialization { raw: String, source: serde_json::Error },
+}
+
+impl std::fmt::Display for SpawnError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SpawnError::Io(e) => write!(f, "io error: {}", e),
+            SpawnError::ExitCode { code, stderr } => {
+                write!(f, "process exited with code {}: {}", code, stderr.trim())
+            }
+            SpawnError::Signal(signal) => write!(f, "process terminated by signal {}", signal),
+            SpawnError::NoStdout => write!(f, "child process has no stdout pipe"),
+            SpawnError::NoStderr => write!(f, "child process has no stderr pipe"),
+            SpawnError::Serialization { raw, source } => {
+                write!(f, "failed to parse event as JSON: {} (line: {})", source, raw)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SpawnError {}
+
+/// Turn an already-spawned child process into a stream of [`ClaudeEvent`]s,
+/// awaiting the child's exit status once both pipes are drained so the
+/// terminal item (if any) carries a precise [`SpawnError`].
+pub fn event_stream(mut child: Child) -> impl Stream<Item = Result<ClaudeEvent, SpawnError>> {
+    stream! {
+        let Some(stdout) = child.stdout.take() else {
+            yield Err(SpawnError::NoStdout);
+            return;
+        };
+        let Some(stderr) = child.stderr.take() else {
+            yield Err(SpawnError::NoStderr);
+            return;
+        };
+
+        let mut stdout_lines = BufReader::new(stdout).lines();
+        let mut stderr_lines = BufReader::new(stderr).lines();
+        let mut stderr_tail = String::new();
+        let mut stdout_done = false;
+        let mut stderr_done = false;
+
+        while !stdout_done || !stderr_done {
+            tokio::select! {
+                line = stdout_lines.next_line(), if !stdout_done => {
+                    match line {
+                        Ok(Some(raw)) => {
+                            let looks_like_json = matches!(raw.trim_start().as_bytes().first(), Some(b'{') | Some(b'['));
+                            if looks_like_json {
+                                match serde_json::from_str::<Value>(&raw) {
+                                    Ok(json) => yield Ok(ClaudeEvent::Stdout { raw, json: Some(json) }),
+                                    Err(e) => {
+                                        // Still surface the raw line itself —
+                                        // a failed parse shouldn't make the
+                                        // content vanish, only the error
+                                        // that's paired with it afterward.
+                                        yield Ok(ClaudeEvent::Stdout { raw: raw.clone(), json: None });
+                                        yield Err(SpawnError::Serialization { raw, source: e });
+                                    }
+                                }
+                            } else {
+                                yield Ok(ClaudeEvent::Stdout { raw, json: None });
+                            }
+                        }
+                        Ok(None) => stdout_done = true,
+                        Err(e) => {
+                            yield Err(SpawnError::Io(e));
+                            stdout_done = true;
+                        }
+                    }
+                }
+                line = stderr_lines.next_line(), if !stderr_done => {
+                    match line {
+                        Ok(Some(raw)) => {
+                            stderr_tail.push_str(&raw);
+                            stderr_tail.push('\n');
+                            yield Ok(ClaudeEvent::Stderr(raw));
+                        }
+                        Ok(None) => stderr_done = true,
+                        Err(e) => {
+                            yield Err(SpawnError::Io(e));
+                            stderr_done = true;
+                        }
+                    }
+                }
+            }
+        }
+
+        match child.wait().await {
+            Ok(status) if status.success() => {}
+            Ok(status) => match status.code() {
+                Some(code) => yield Err(SpawnError::ExitCode { code, stderr: stderr_tail }),
+                None => {
+                    #[cfg(unix)]
+                    {
+                        use std::os::unix::process::ExitStatusExt;
+                        if let Some(signal) = status.signal() {
+                            yield Err(SpawnError::Signal(signal));
+                        }
+                    }
+                }
+            },
+            Err(e) => yield Err(SpawnError::Io(e)),
+        }
+    }
+}
@@ -1,13 +1,21 @@
-use log::{debug, error, info, warn};
+use futures_util::StreamExt;
+use log::{debug, info, warn};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::pin::Pin;
 use std::sync::Arc;
 use tauri::{AppHandle, Emitter};
-use tokio::io::{AsyncBufReadExt, BufReader};
 use tokio::process::Command;
 use tokio::sync::Mutex;
 
+use crate::backoff::{retry_with_backoff, Backoff};
+use crate::claude_stream::{event_stream, ClaudeEvent, SpawnError};
+use crate::err_chan::{ErrChan, Severity};
+use crate::process_tree::{descendants_of, ChildHandle};
+use crate::worker_manager::WorkerManager;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SubAgentInfo {
     pub parent_session_id: String,
@@ -16,30 +24,92 @@ pub struct SubAgentInfo {
     pub subagent_type: Option<String>,
     pub process_id: Option<u32>,
     pub output_buffer: Vec<String>,
+    /// The sub-agent that spawned this one, if any. `None` means this is a
+    /// root Task spawned directly from the top-level session.
+    pub parent_subagent_id: Option<String>,
+    /// IDs of sub-agents this one has itself spawned via a nested Task call.
+    pub children: Vec<String>,
+    /// Set when a still-running sub-agent's parent finished first, so the
+    /// frontend can render it as detached rather than silently vanishing.
+    pub orphaned: bool,
+}
+
+/// A sub-agent and its descendants, as returned by [`SubAgentRegistry::get_tree`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubAgentNode {
+    pub subagent_id: String,
+    pub info: SubAgentInfo,
+    pub children: Vec<SubAgentNode>,
 }
 
 /// Global registry for tracking sub-agent sessions
 pub struct SubAgentRegistry {
     active_subagents: Arc<Mutex<HashMap<String, SubAgentInfo>>>,
+    /// PID of the top-level Claude process for each session, so we know which
+    /// process tree to walk when looking for sub-agent children.
+    root_pids: Arc<Mutex<HashMap<String, u32>>>,
+    /// Which sub-agent (if any) has already claimed a discovered child PID.
+    /// Shared across every `capture_subagent_output` poll loop so that when
+    /// two sub-agents are active concurrently, only the one that discovers a
+    /// PID first attaches to it instead of both racing to read the same
+    /// `/proc/<pid>/fd/1`.
+    claimed_pids: Arc<Mutex<HashMap<u32, String>>>,
 }
 
 impl SubAgentRegistry {
     pub fn new() -> Self {
         Self {
             active_subagents: Arc::new(Mutex::new(HashMap::new())),
+            root_pids: Arc::new(Mutex::new(HashMap::new())),
+            claimed_pids: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Record the PID of the top-level Claude process spawned for `session_id`.
+    pub async fn set_root_pid(&self, session_id: String, pid: u32) {
+        self.root_pids.lock().await.insert(session_id, pid);
+    }
+
+    /// Look up the root PID recorded for a session, if any.
+    pub async fn get_root_pid(&self, session_id: &str) -> Option<u32> {
+        self.root_pids.lock().await.get(session_id).copied()
+    }
+
+    /// Try to claim `pid` on behalf of `subagent_id`. Returns `true` if this
+    /// is the sub-agent that now owns the PID (either it just claimed it, or
+    /// it already did), `false` if some other sub-agent got there first.
+    pub async fn claim_pid(&self, pid: u32, subagent_id: &str) -> bool {
+        let mut claimed = self.claimed_pids.lock().await;
+        match claimed.get(&pid) {
+            Some(owner) => owner == subagent_id,
+            None => {
+                claimed.insert(pid, subagent_id.to_string());
+                true
+            }
         }
     }
 
-    /// Register a new sub-agent when Task tool is detected
+    /// Release every PID claimed by `subagent_id`, e.g. once its monitor
+    /// loop exits.
+    pub async fn release_pids(&self, subagent_id: &str) {
+        let mut claimed = self.claimed_pids.lock().await;
+        claimed.retain(|_, owner| owner != subagent_id);
+    }
+
+    /// Register a new sub-agent when a Task tool is detected. `parent_subagent_id`
+    /// is `Some` when the Task originated from within another active
+    /// sub-agent's own output stream, making this one a nested child rather
+    /// than a root.
     pub async fn register_subagent(
         &self,
         parent_session_id: String,
         tool_id: String,
         task_description: String,
         subagent_type: Option<String>,
+        parent_subagent_id: Option<String>,
     ) -> String {
         let subagent_id = format!("{}:{}", parent_session_id, tool_id);
-        
+
         let info = SubAgentInfo {
             parent_session_id,
             tool_id,
@@ -47,11 +117,20 @@ impl SubAgentRegistry {
             subagent_type,
             process_id: None,
             output_buffer: Vec::new(),
+            parent_subagent_id: parent_subagent_id.clone(),
+            children: Vec::new(),
+            orphaned: false,
         };
 
         let mut registry = self.active_subagents.lock().await;
         registry.insert(subagent_id.clone(), info);
-        
+
+        if let Some(parent_id) = parent_subagent_id {
+            if let Some(parent) = registry.get_mut(&parent_id) {
+                parent.children.push(subagent_id.clone());
+            }
+        }
+
         info!("Registered sub-agent: {}", subagent_id);
         subagent_id
     }
@@ -64,29 +143,115 @@ impl SubAgentRegistry {
         }
     }
 
+    /// Record the real child PID discovered for a sub-agent, once
+    /// `stream_child_output` successfully attaches to one. A sub-agent can
+    /// have several descendant PIDs; this records the first one discovered.
+    pub async fn set_process_id(&self, subagent_id: &str, pid: u32) {
+        let mut registry = self.active_subagents.lock().await;
+        if let Some(info) = registry.get_mut(subagent_id) {
+            if info.process_id.is_none() {
+                info.process_id = Some(pid);
+            }
+        }
+    }
+
     /// Get sub-agent info
     pub async fn get_subagent(&self, subagent_id: &str) -> Option<SubAgentInfo> {
         let registry = self.active_subagents.lock().await;
         registry.get(subagent_id).cloned()
     }
 
-    /// Remove sub-agent when task completes
+    /// Remove a sub-agent when its task completes. Any still-running
+    /// children are re-rooted under this sub-agent's own parent (or
+    /// promoted to top-level roots, if it had none) and marked orphaned,
+    /// rather than left pointing at a now-deleted parent where `get_tree`
+    /// would never find them again.
     pub async fn remove_subagent(&self, subagent_id: &str) {
         let mut registry = self.active_subagents.lock().await;
-        registry.remove(subagent_id);
+        let Some(removed) = registry.remove(subagent_id) else {
+            return;
+        };
+
+        // Detach from our own parent's child list so it doesn't keep a
+        // dangling reference to us.
+        if let Some(parent_id) = &removed.parent_subagent_id {
+            if let Some(parent) = registry.get_mut(parent_id) {
+                parent.children.retain(|id| id != subagent_id);
+            }
+        }
+
+        // Splice our children onto our parent (or promote them to roots),
+        // and mark them orphaned so the frontend knows they detached from
+        // their original lineage.
+        for child_id in &removed.children {
+            if let Some(child) = registry.get_mut(child_id) {
+                child.orphaned = true;
+                child.parent_subagent_id = removed.parent_subagent_id.clone();
+            }
+        }
+        if let Some(parent_id) = &removed.parent_subagent_id {
+            if let Some(parent) = registry.get_mut(parent_id) {
+                parent.children.extend(removed.children.iter().cloned());
+            }
+        }
+
         info!("Removed sub-agent: {}", subagent_id);
     }
+
+    /// Build the full sub-agent hierarchy for `root_session_id`, i.e. every
+    /// root Task spawned directly from that session and its nested
+    /// descendants, for the frontend to render as a tree.
+    pub async fn get_tree(&self, root_session_id: &str) -> Vec<SubAgentNode> {
+        let registry = self.active_subagents.lock().await;
+
+        fn build_node(id: &str, registry: &HashMap<String, SubAgentInfo>) -> Option<SubAgentNode> {
+            let info = registry.get(id)?.clone();
+            let children = info
+                .children
+                .iter()
+                .filter_map(|child_id| build_node(child_id, registry))
+                .collect();
+            Some(SubAgentNode {
+                subagent_id: id.to_string(),
+                info,
+                children,
+            })
+        }
+
+        registry
+            .iter()
+            .filter(|(_, info)| {
+                info.parent_session_id == root_session_id && info.parent_subagent_id.is_none()
+            })
+            .filter_map(|(id, _)| build_node(id, &registry))
+            .collect()
+    }
 }
 
-/// Monitor Claude output for Task tool usage and capture sub-agent output
-pub async fn monitor_for_subagents(
+/// Monitor Claude output for Task tool usage and capture sub-agent output.
+///
+/// `current_subagent_id` identifies the sub-agent whose own output stream
+/// `line` came from, if any; a nested Task detected there is registered as
+/// that sub-agent's child instead of a root of `session_id`.
+///
+/// Returns a boxed, type-erased future rather than being declared `async fn`.
+/// `stream_child_output` feeds lines from a sub-agent's own output back
+/// through this function, which can in turn spawn another
+/// `capture_subagent_output` -> `stream_child_output` worker for a nested
+/// Task; an `async fn` calling back into that cycle makes rustc try to
+/// expand an infinitely-recursive opaque type. Boxing this call breaks the
+/// cycle at a fixed-size, heap-allocated boundary.
+pub fn monitor_for_subagents(
     app: AppHandle,
     session_id: String,
-    line: &str,
+    line: String,
     registry: Arc<SubAgentRegistry>,
-) {
+    worker_manager: WorkerManager,
+    current_subagent_id: Option<String>,
+) -> Pin<Box<dyn Future<Output = ()> + Send>> {
+    Box::pin(async move {
     // Try to parse the line as JSON
-    if let Ok(msg) = serde_json::from_str::<Value>(line) {
+    if let Ok(msg) = serde_json::from_str::<Value>(&line) {
         // Check if this is a tool_use message for Task
         if msg["type"] == "assistant" {
             if let Some(content) = msg["message"]["content"].as_array() {
@@ -110,13 +275,17 @@ pub async fn monitor_for_subagents(
                             session_id, task_description, subagent_type
                         );
 
-                        // Register the sub-agent
+                        // Register the sub-agent, attaching it under
+                        // `current_subagent_id` if this Task was detected
+                        // inside another sub-agent's own output rather than
+                        // the top-level session.
                         let subagent_id = registry
                             .register_subagent(
                                 session_id.clone(),
                                 tool_id.clone(),
                                 task_description,
                                 subagent_type,
+                                current_subagent_id.clone(),
                             )
                             .await;
 
@@ -129,16 +298,27 @@ pub async fn monitor_for_subagents(
                                 "description": input["description"],
                                 "prompt": input["prompt"],
                                 "subagent_type": input["subagent_type"],
+                                "parent_subagent_id": current_subagent_id,
                             }),
                         );
+                        emit_tree_updated(&app, &registry, &session_id).await;
 
-                        // Start monitoring for sub-agent output
-                        tokio::spawn(capture_subagent_output(
-                            app.clone(),
-                            session_id.clone(),
-                            subagent_id,
-                            registry.clone(),
-                        ));
+                        // Start monitoring for sub-agent output, tracked so
+                        // the worker manager can cancel it if the session
+                        // closes before the sub-agent finishes.
+                        worker_manager
+                            .spawn_worker(
+                                session_id.clone(),
+                                format!("subagent:{}", subagent_id),
+                                capture_subagent_output(
+                                    app.clone(),
+                                    session_id.clone(),
+                                    subagent_id,
+                                    registry.clone(),
+                                    worker_manager.clone(),
+                                ),
+                            )
+                            .await;
                     }
                 }
             }
@@ -168,6 +348,7 @@ pub async fn monitor_for_subagents(
 
                                 // Clean up
                                 registry.remove_subagent(&subagent_id).await;
+                                emit_tree_updated(&app, &registry, &session_id).await;
                             }
                         }
                     }
@@ -175,113 +356,383 @@ pub async fn monitor_for_subagents(
             }
         }
     }
+    })
+}
+
+/// Emit `subagent-tree-updated:<session>` with the current hierarchy rooted
+/// at `session_id`, so the frontend can re-render whenever the topology
+/// changes (a sub-agent starts, nests a child, or completes).
+async fn emit_tree_updated(app: &AppHandle, registry: &SubAgentRegistry, session_id: &str) {
+    let tree = registry.get_tree(session_id).await;
+    let _ = app.emit(
+        &format!("subagent-tree-updated:{}", session_id),
+        serde_json::json!({ "tree": tree }),
+    );
 }
 
-/// Capture output from a sub-agent process
+/// Capture output from a sub-agent process by walking the OS process tree
+/// rooted at the session's Claude process, discovering newly-spawned
+/// children, and streaming their real stdout. Their stderr is not attached.
 async fn capture_subagent_output(
     app: AppHandle,
     parent_session_id: String,
     subagent_id: String,
     registry: Arc<SubAgentRegistry>,
+    worker_manager: WorkerManager,
 ) {
-    // In reality, Claude spawns sub-agents as separate processes
-    // We need to detect and capture their output
-    
-    // For now, we'll monitor the parent session's output for patterns
-    // that indicate sub-agent activity
-    
     info!("Starting sub-agent output capture for {}", subagent_id);
-    
-    // This is a simplified version - in production, we'd need to:
-    // 1. Find the actual sub-process that Claude spawned
-    // 2. Attach to its stdout/stderr
-    // 3. Stream that output separately
-    
-    // Emit periodic status updates
-    let mut counter = 0;
+
+    let Some(root_pid) = registry.get_root_pid(&parent_session_id).await else {
+        warn!(
+            "No root process recorded for session {}, cannot attach to sub-agent {}",
+            parent_session_id, subagent_id
+        );
+        return;
+    };
+
+    let poll_interval = tokio::time::Duration::from_millis(500);
+    let mut attached: HashSet<u32> = HashSet::new();
+    let mut readers: Vec<tokio::task::JoinHandle<()>> = Vec::new();
+
     loop {
-        tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
-        
-        // Check if sub-agent still exists
+        // Stop polling once the tool_result arrived and the registry entry
+        // was removed.
         if registry.get_subagent(&subagent_id).await.is_none() {
             break;
         }
-        
-        // Emit a heartbeat/status
-        let _ = app.emit(
-            &format!("subagent-output:{}", parent_session_id),
-            serde_json::json!({
-                "subagent_id": subagent_id,
-                "type": "status",
-                "message": format!("Sub-agent working... ({}s)", counter * 2),
-            }),
+
+        for pid in descendants_of(root_pid) {
+            // Skip PIDs this sub-agent already has a reader on.
+            if attached.contains(&pid) {
+                continue;
+            }
+
+            // Every active sub-agent walks the *same* process tree, so two
+            // of them can discover the same PID in the same tick. Claim it
+            // through the registry first so only one sub-agent ever attaches
+            // to a given PID at a time. Reparenting is not tracked:
+            // `descendants_of` follows only current parent links, so a
+            // grandchild reparented away from `root_pid`'s tree after its
+            // immediate parent exits is no longer discovered.
+            if !registry.claim_pid(pid, &subagent_id).await {
+                continue;
+            }
+            attached.insert(pid);
+
+            let app = app.clone();
+            let parent_session_id = parent_session_id.clone();
+            let subagent_id = subagent_id.clone();
+            let registry = registry.clone();
+            let worker_manager = worker_manager.clone();
+
+            readers.push(tokio::spawn(async move {
+                stream_child_output(app, parent_session_id, subagent_id, registry, worker_manager, pid).await;
+            }));
+        }
+
+        tokio::time::sleep(poll_interval).await;
+    }
+
+    for handle in readers {
+        handle.abort();
+    }
+    registry.release_pids(&subagent_id).await;
+}
+
+/// Stream a single discovered child process's stdout into the sub-agent's
+/// output buffer and the `subagent-output:` event, until it exits or the
+/// sub-agent is reaped from the registry. Lines are also fed back through
+/// [`monitor_for_subagents`] so a Task nested inside this sub-agent's own
+/// output is attached as its child rather than a new root.
+async fn stream_child_output(
+    app: AppHandle,
+    parent_session_id: String,
+    subagent_id: String,
+    registry: Arc<SubAgentRegistry>,
+    worker_manager: WorkerManager,
+    pid: u32,
+) {
+    debug!("Attaching to sub-agent child process {} for {}", pid, subagent_id);
+
+    let Some(handle) = ChildHandle::attach(pid) else {
+        warn!(
+            "Could not attach to stdout of pid {} for sub-agent {}",
+            pid, subagent_id
         );
-        
-        counter += 1;
-        if counter > 30 {
-            // Timeout after 60 seconds
+        return;
+    };
+
+    registry.set_process_id(&subagent_id, pid).await;
+
+    let mut lines = handle.into_lines();
+    loop {
+        match lines.next_line().await {
+            Ok(Some(line)) => {
+                registry.add_output(&subagent_id, line.clone()).await;
+                let _ = app.emit(
+                    &format!("subagent-output:{}", parent_session_id),
+                    serde_json::json!({
+                        "subagent_id": subagent_id,
+                        "pid": pid,
+                        "type": "stdout",
+                        "message": line,
+                    }),
+                );
+
+                monitor_for_subagents(
+                    app.clone(),
+                    parent_session_id.clone(),
+                    line.clone(),
+                    registry.clone(),
+                    worker_manager.clone(),
+                    Some(subagent_id.clone()),
+                )
+                .await;
+            }
+            Ok(None) => break,
+            Err(e) => {
+                warn!("Error reading output of sub-agent process {}: {}", pid, e);
+                break;
+            }
+        }
+
+        if registry.get_subagent(&subagent_id).await.is_none() {
             break;
         }
     }
 }
 
-/// Enhanced spawn function that detects sub-agent spawning
+/// Enhanced spawn function that detects sub-agent spawning.
+///
+/// This is a thin Tauri-facing consumer over [`event_stream`]: it owns the
+/// retry-on-spawn-failure policy and the sub-agent/registry/emit side
+/// effects, while the stream itself stays reusable and testable on its own.
 pub async fn spawn_claude_with_subagent_detection(
     app: AppHandle,
     mut cmd: Command,
     session_id: String,
     registry: Arc<SubAgentRegistry>,
+    err_chan: ErrChan,
+    worker_manager: WorkerManager,
 ) -> Result<(), String> {
-    use tokio::io::AsyncBufReadExt;
-
-    // Spawn the process
-    let mut child = cmd
-        .spawn()
-        .map_err(|e| format!("Failed to spawn Claude: {}", e))?;
-
-    // Get stdout and stderr
-    let stdout = child.stdout.take().ok_or("Failed to get stdout")?;
-    let stderr = child.stderr.take().ok_or("Failed to get stderr")?;
-
-    let stdout_reader = BufReader::new(stdout);
-    let stderr_reader = BufReader::new(stderr);
-
-    // Spawn task to read stdout with sub-agent detection
-    let app_handle = app.clone();
-    let session_id_clone = session_id.clone();
-    let registry_clone = registry.clone();
-    
-    tokio::spawn(async move {
-        let mut lines = stdout_reader.lines();
-        while let Ok(Some(line)) = lines.next_line().await {
-            debug!("Claude stdout: {}", line);
-            
-            // Monitor for sub-agent Task tool usage
-            monitor_for_subagents(
-                app_handle.clone(),
-                session_id_clone.clone(),
-                &line,
-                registry_clone.clone(),
-            ).await;
-            
-            // Emit normal output
-            let _ = app_handle.emit(&format!("claude-output:{}", session_id_clone), &line);
-            let _ = app_handle.emit("claude-output", &line);
-        }
-    });
-
-    // Handle stderr similarly
-    let app_handle_stderr = app.clone();
-    let session_id_stderr = session_id.clone();
-    
-    tokio::spawn(async move {
-        let mut lines = stderr_reader.lines();
-        while let Ok(Some(line)) = lines.next_line().await {
-            error!("Claude stderr: {}", line);
-            let _ = app_handle_stderr.emit(&format!("claude-error:{}", session_id_stderr), &line);
-            let _ = app_handle_stderr.emit("claude-error", &line);
+    // Spawn the process, retrying with exponential backoff in case the
+    // Claude binary is momentarily unavailable or rate-limited.
+    let backoff = Backoff::new(
+        std::time::Duration::from_millis(500),
+        std::time::Duration::from_secs(30),
+        2.0,
+    )
+    .with_jitter(0.2);
+
+    let child = match retry_with_backoff(backoff, 5, || {
+        // `cmd.spawn()` must run synchronously inside the closure: `attempt`
+        // is an `FnMut`, so a reference it captures (here, `cmd`) cannot
+        // escape into the returned future, only an already-resolved,
+        // owned value can.
+        let result = cmd.spawn();
+        async move { result }
+    })
+    .await
+    {
+        Ok(child) => child,
+        Err(e) => {
+            let message = format!("Failed to spawn Claude: {}", e);
+            err_chan.send("spawn", session_id.clone(), message.clone(), Severity::Fatal);
+            return Err(message);
         }
-    });
+    };
+
+    // Record the root PID so sub-agent discovery knows which process tree to
+    // walk once a Task tool_use is detected.
+    if let Some(pid) = child.id() {
+        registry.set_root_pid(session_id.clone(), pid).await;
+    }
+
+    let mut events = Box::pin(event_stream(child));
+    let worker_manager_inner = worker_manager.clone();
+
+    worker_manager
+        .spawn_worker(session_id.clone(), "io", async move {
+            while let Some(event) = events.next().await {
+                match event {
+                    Ok(ClaudeEvent::Stdout { raw, .. }) => {
+                        debug!("Claude stdout: {}", raw);
+
+                        // Monitor for sub-agent Task tool usage
+                        monitor_for_subagents(
+                            app.clone(),
+                            session_id.clone(),
+                            raw.clone(),
+                            registry.clone(),
+                            worker_manager_inner.clone(),
+                            None,
+                        )
+                        .await;
+
+                        // Emit normal output
+                        let _ = app.emit(&format!("claude-output:{}", session_id), &raw);
+                        let _ = app.emit("claude-output", &raw);
+                    }
+                    Ok(ClaudeEvent::Stderr(line)) => {
+                        err_chan.send("stderr", session_id.clone(), line, Severity::Warning);
+                    }
+                    Err(spawn_err) => {
+                        err_chan.send(
+                            "process",
+                            session_id.clone(),
+                            spawn_err.to_string(),
+                            spawn_error_severity(&spawn_err),
+                        );
+                    }
+                }
+            }
+
+            // The event stream only ends once the Claude process itself has
+            // exited, so this is the natural point to tear down anything
+            // left running for the session (e.g. a sub-agent monitor still
+            // polling because its tool_result never arrived).
+            close_session(&session_id, &worker_manager_inner).await;
+        })
+        .await;
 
     Ok(())
+}
+
+/// Abort every background worker registered for `session_id` — its stdout
+/// reader and any still-running sub-agent monitors — so a session tears
+/// down deterministically instead of lingering until an ad-hoc timeout.
+/// Safe to call both when a session's Claude process exits on its own and
+/// when the frontend explicitly closes a session early.
+pub async fn close_session(session_id: &str, worker_manager: &WorkerManager) {
+    worker_manager.cancel_session(session_id).await;
+}
+
+/// Map a terminal stream error to the severity the frontend should surface
+/// it with.
+fn spawn_error_severity(err: &SpawnError) -> Severity {
+    match err {
+        SpawnError::Io(_) | SpawnError::NoStdout | SpawnError::NoStderr => Severity::Fatal,
+        SpawnError::ExitCode { .. } | SpawnError::Signal(_) => Severity::Error,
+        SpawnError::Serialization { .. } => Severity::Warning,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A root Task and a nested Task under it should both show up in
+    /// `get_tree`, with the nested one as the root's only child.
+    #[tokio::test]
+    async fn get_tree_nests_child_under_root() {
+        let registry = SubAgentRegistry::new();
+        let root_id = registry
+            .register_subagent(
+                "session-1".to_string(),
+                "tool-root".to_string(),
+                "root task".to_string(),
+                None,
+                None,
+            )
+            .await;
+        let child_id = registry
+            .register_subagent(
+                "session-1".to_string(),
+                "tool-child".to_string(),
+                "child task".to_string(),
+                None,
+                Some(root_id.clone()),
+            )
+            .await;
+
+        let tree = registry.get_tree("session-1").await;
+        assert_eq!(tree.len(), 1);
+        assert_eq!(tree[0].subagent_id, root_id);
+        assert_eq!(tree[0].children.len(), 1);
+        assert_eq!(tree[0].children[0].subagent_id, child_id);
+    }
+
+    /// Removing a sub-agent with still-running children must re-root those
+    /// children onto its own parent (rather than leave them pointing at a
+    /// now-deleted id) and mark them orphaned.
+    #[tokio::test]
+    async fn remove_subagent_reparents_children_onto_grandparent() {
+        let registry = SubAgentRegistry::new();
+        let root_id = registry
+            .register_subagent(
+                "session-1".to_string(),
+                "tool-root".to_string(),
+                "root task".to_string(),
+                None,
+                None,
+            )
+            .await;
+        let middle_id = registry
+            .register_subagent(
+                "session-1".to_string(),
+                "tool-middle".to_string(),
+                "middle task".to_string(),
+                None,
+                Some(root_id.clone()),
+            )
+            .await;
+        let leaf_id = registry
+            .register_subagent(
+                "session-1".to_string(),
+                "tool-leaf".to_string(),
+                "leaf task".to_string(),
+                None,
+                Some(middle_id.clone()),
+            )
+            .await;
+
+        registry.remove_subagent(&middle_id).await;
+
+        let leaf = registry.get_subagent(&leaf_id).await.expect("leaf still tracked");
+        assert!(leaf.orphaned);
+        assert_eq!(leaf.parent_subagent_id.as_deref(), Some(root_id.as_str()));
+
+        // The tree should now show the leaf directly under the root, with
+        // the removed middle node gone entirely.
+        let tree = registry.get_tree("session-1").await;
+        assert_eq!(tree.len(), 1);
+        assert_eq!(tree[0].subagent_id, root_id);
+        assert_eq!(tree[0].children.len(), 1);
+        assert_eq!(tree[0].children[0].subagent_id, leaf_id);
+    }
+
+    /// Removing a root sub-agent (no parent of its own) should promote its
+    /// children to top-level roots instead of dropping them.
+    #[tokio::test]
+    async fn remove_subagent_promotes_children_to_roots_when_no_grandparent() {
+        let registry = SubAgentRegistry::new();
+        let root_id = registry
+            .register_subagent(
+                "session-1".to_string(),
+                "tool-root".to_string(),
+                "root task".to_string(),
+                None,
+                None,
+            )
+            .await;
+        let child_id = registry
+            .register_subagent(
+                "session-1".to_string(),
+                "tool-child".to_string(),
+                "child task".to_string(),
+                None,
+                Some(root_id.clone()),
+            )
+            .await;
+
+        registry.remove_subagent(&root_id).await;
+
+        let child = registry.get_subagent(&child_id).await.expect("child still tracked");
+        assert!(child.orphaned);
+        assert!(child.parent_subagent_id.is_none());
+
+        let tree = registry.get_tree("session-1").await;
+        assert_eq!(tree.len(), 1);
+        assert_eq!(tree[0].subagent_id, child_id);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,93 @@
+//! Centralized error-reporting channel.
+//!
+//! Instead of scattering `error!(...)` logs and one-off `app.emit` calls
+//! across every module, producers send a structured [`ErrReport`] over an
+//! mpsc channel and a single long-lived reporter task forwards it to the
+//! frontend, retrying delivery a bounded number of times before falling back
+//! to a local log line so errors are never silently dropped.
+
+use log::{error, warn};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+use tokio::sync::mpsc;
+
+/// How severe a reported error is, used by the frontend to decide how loudly
+/// to surface it.
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Warning,
+    Error,
+    Fatal,
+}
+
+/// A single structured error to report.
+#[derive(Debug, Clone, Serialize)]
+pub struct ErrReport {
+    /// Module or subsystem the error originated from, e.g. "spawn", "stderr".
+    pub source: String,
+    pub session_id: String,
+    pub message: String,
+    pub severity: Severity,
+}
+
+/// Handle producers use to fire-and-forget errors into the reporter.
+#[derive(Clone)]
+pub struct ErrChan {
+    sender: mpsc::UnboundedSender<ErrReport>,
+}
+
+impl ErrChan {
+    /// Spawn the reporter task and return a handle for producers to clone.
+    pub fn spawn(app: AppHandle) -> Self {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        tokio::spawn(report_loop(app, receiver));
+        Self { sender }
+    }
+
+    /// Report an error. Never blocks and never fails the caller; if the
+    /// reporter task is gone the report is simply dropped (there is no
+    /// frontend left to tell).
+    pub fn send(&self, source: impl Into<String>, session_id: impl Into<String>, message: impl Into<String>, severity: Severity) {
+        let report = ErrReport {
+            source: source.into(),
+            session_id: session_id.into(),
+            message: message.into(),
+            severity,
+        };
+        if self.sender.send(report).is_err() {
+            warn!("ErrChan reporter task is gone, dropping error report");
+        }
+    }
+}
+
+const MAX_DELIVERY_ATTEMPTS: u32 = 3;
+const RETRY_DELAY: std::time::Duration = std::time::Duration::from_millis(200);
+
+async fn report_loop(app: AppHandle, mut receiver: mpsc::UnboundedReceiver<ErrReport>) {
+    while let Some(report) = receiver.recv().await {
+        let mut delivered = false;
+        for attempt in 1..=MAX_DELIVERY_ATTEMPTS {
+            match app.emit(&format!("session-error:{}", report.session_id), &report) {
+                Ok(()) => {
+                    delivered = true;
+                    break;
+                }
+                Err(e) => {
+                    error!(
+                        "Failed to emit error report (attempt {}/{}): {}",
+                        attempt, MAX_DELIVERY_ATTEMPTS, e
+                    );
+                    tokio::time::sleep(RETRY_DELAY).await;
+                }
+            }
+        }
+
+        if !delivered {
+            warn!(
+                "Giving up on delivering error report from {} after {} attempts: {}",
+                report.source, MAX_DELIVERY_ATTEMPTS, report.message
+            );
+        }
+    }
+}
@@ -0,0 +1,118 @@
+//! OS process-tree discovery used to find the real child processes Claude
+//! spawns for sub-agents, so we can attach to their stdout instead of faking
+//! progress updates. Their stderr is not attached.
+
+use std::collections::{HashMap, HashSet};
+use tokio::io::{AsyncBufReadExt, BufReader, Lines};
+
+/// An attached handle to a discovered child process's stdout.
+pub struct ChildHandle {
+    pid: u32,
+    #[cfg(target_os = "linux")]
+    file: std::fs::File,
+}
+
+impl ChildHandle {
+    /// Try to attach to `pid`'s stdout. Returns `None` if the platform has no
+    /// supported attachment mechanism or the process has already exited.
+    #[cfg(target_os = "linux")]
+    pub fn attach(pid: u32) -> Option<Self> {
+        let file = std::fs::File::open(format!("/proc/{pid}/fd/1")).ok()?;
+        Some(Self { pid, file })
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn attach(_pid: u32) -> Option<Self> {
+        // Unlike Linux's `/proc/<pid>/fd/1`, neither macOS nor Windows expose
+        // a file-like handle onto an arbitrary unrelated process's stdout
+        // without additional setup (e.g. a named pipe the child was launched
+        // with, or a debugger-level API). `descendants_of` still works on
+        // these platforms via `sysinfo`; only output attachment is
+        // unsupported here.
+        None
+    }
+
+    #[cfg(target_os = "linux")]
+    pub fn into_lines(self) -> Lines<BufReader<tokio::fs::File>> {
+        BufReader::new(tokio::fs::File::from_std(self.file)).lines()
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn into_lines(self) -> Lines<BufReader<tokio::fs::File>> {
+        unreachable!("ChildHandle::attach never succeeds on this platform")
+    }
+
+    pub fn pid(&self) -> u32 {
+        self.pid
+    }
+}
+
+/// Return the PIDs of every descendant of `root_pid` (children,
+/// grandchildren, ...), discovered by walking `/proc`.
+#[cfg(target_os = "linux")]
+pub fn descendants_of(root_pid: u32) -> HashSet<u32> {
+    let mut parent_of: HashMap<u32, u32> = HashMap::new();
+
+    let Ok(entries) = std::fs::read_dir("/proc") else {
+        return HashSet::new();
+    };
+
+    for entry in entries.flatten() {
+        let Ok(pid) = entry.file_name().to_string_lossy().parse::<u32>() else {
+            continue;
+        };
+        if let Some(ppid) = read_ppid(pid) {
+            parent_of.insert(pid, ppid);
+        }
+    }
+
+    let mut descendants = HashSet::new();
+    let mut frontier = vec![root_pid];
+    while let Some(parent) = frontier.pop() {
+        for (&pid, &ppid) in &parent_of {
+            if ppid == parent && descendants.insert(pid) {
+                frontier.push(pid);
+            }
+        }
+    }
+    descendants
+}
+
+/// Return the PIDs of every descendant of `root_pid`, discovered via
+/// `sysinfo`'s process table (which wraps `libproc` on macOS and the
+/// toolhelp/NtQuery APIs on Windows).
+#[cfg(not(target_os = "linux"))]
+pub fn descendants_of(root_pid: u32) -> HashSet<u32> {
+    use sysinfo::System;
+
+    let mut system = System::new();
+    system.refresh_processes(sysinfo::ProcessesToUpdate::All, true);
+
+    let mut parent_of: HashMap<u32, u32> = HashMap::new();
+    for (pid, process) in system.processes() {
+        if let Some(parent) = process.parent() {
+            parent_of.insert(pid.as_u32(), parent.as_u32());
+        }
+    }
+
+    let mut descendants = HashSet::new();
+    let mut frontier = vec![root_pid];
+    while let Some(parent) = frontier.pop() {
+        for (&pid, &ppid) in &parent_of {
+            if ppid == parent && descendants.insert(pid) {
+                frontier.push(pid);
+            }
+        }
+    }
+    descendants
+}
+
+/// Parse the PPID out of `/proc/<pid>/stat`. The second field, `comm`, is
+/// parenthesized and may itself contain spaces or parens, so we split after
+/// the *last* `)` rather than tokenizing naively.
+#[cfg(target_os = "linux")]
+fn read_ppid(pid: u32) -> Option<u32> {
+    let stat = std::fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+    let after_comm = stat.rsplit_once(')')?.1;
+    after_comm.split_whitespace().nth(1)?.parse().ok()
+}
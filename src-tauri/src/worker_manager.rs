@@ -0,0 +1,113 @@
+//! Registry of background workers (stdout/stderr readers, sub-agent
+//! monitors, ...) spawned per session, so a session can be torn down
+//! deterministically instead of leaking tasks until their ad-hoc timeout.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tokio::task::AbortHandle;
+
+/// A single tracked background task.
+#[derive(Clone)]
+pub struct WorkerHandle {
+    pub role: String,
+    abort: AbortHandle,
+}
+
+/// Owns every spawned background worker, grouped by session id.
+#[derive(Clone)]
+pub struct WorkerManager {
+    workers: Arc<Mutex<HashMap<String, Vec<WorkerHandle>>>>,
+}
+
+impl WorkerManager {
+    pub fn new() -> Self {
+        Self {
+            workers: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Spawn `future` as a tracked background worker for `session_id`,
+    /// labeled with a human-readable `role` (e.g. "stdout", "stderr",
+    /// "subagent:<id>").
+    pub async fn spawn_worker<F>(&self, session_id: impl Into<String>, role: impl Into<String>, future: F)
+    where
+        F: std::future::Future<Output = ()> + Send + 'static,
+    {
+        let session_id = session_id.into();
+        let role = role.into();
+        let join_handle = tokio::spawn(future);
+        let handle = WorkerHandle {
+            role,
+            abort: join_handle.abort_handle(),
+        };
+
+        let mut workers = self.workers.lock().await;
+        workers.entry(session_id).or_default().push(handle);
+    }
+
+    /// Abort every worker registered for `session_id` and drop its entry.
+    pub async fn cancel_session(&self, session_id: &str) {
+        let mut workers = self.workers.lock().await;
+        if let Some(session_workers) = workers.remove(session_id) {
+            for worker in session_workers {
+                worker.abort.abort();
+            }
+        }
+    }
+
+    /// List every tracked worker as `(session_id, role)` pairs, for frontend
+    /// introspection.
+    pub async fn list_workers(&self) -> Vec<(String, String)> {
+        let workers = self.workers.lock().await;
+        workers
+            .iter()
+            .flat_map(|(session_id, handles)| {
+                handles
+                    .iter()
+                    .map(move |h| (session_id.clone(), h.role.clone()))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    /// Cancelling a session must abort every worker registered under it and
+    /// drop it from `list_workers`, while leaving other sessions untouched.
+    #[tokio::test]
+    async fn cancel_session_aborts_only_its_own_workers() {
+        let manager = WorkerManager::new();
+        let a_ran_to_completion = Arc::new(AtomicBool::new(false));
+        let b_ran_to_completion = Arc::new(AtomicBool::new(false));
+
+        let a_flag = a_ran_to_completion.clone();
+        manager
+            .spawn_worker("session-a", "io", async move {
+                tokio::time::sleep(std::time::Duration::from_secs(3600)).await;
+                a_flag.store(true, Ordering::SeqCst);
+            })
+            .await;
+
+        let b_flag = b_ran_to_completion.clone();
+        manager
+            .spawn_worker("session-b", "io", async move {
+                b_flag.store(true, Ordering::SeqCst);
+            })
+            .await;
+
+        // Let session-b's already-ready worker actually finish.
+        tokio::task::yield_now().await;
+
+        manager.cancel_session("session-a").await;
+
+        let remaining = manager.list_workers().await;
+        assert!(remaining.iter().all(|(session, _)| session != "session-a"));
+
+        assert!(!a_ran_to_completion.load(Ordering::SeqCst));
+        assert!(b_ran_to_completion.load(Ordering::SeqCst));
+    }
+}